@@ -1,9 +1,22 @@
-use actix_web::{middleware, web, App, HttpResponse, HttpServer};
+use actix_web::http::StatusCode;
+use actix_web::{middleware, web, App, HttpRequest, HttpResponse, HttpServer, ResponseError};
+use async_graphql::{Context, EmptySubscription, Object, Schema, SimpleObject};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
 use std::convert::TryFrom;
 use std::fmt;
+use std::time::Instant;
+
+mod metrics;
+use metrics::{Metrics, RequestMetrics};
+
+/// Shared pool of SQLite connections, checked out per request.
+type DbPool = r2d2::Pool<SqliteConnectionManager>;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct CreateMazeHttpRequest {
@@ -54,28 +67,22 @@ enum MazeCellKind {
 }
 
 impl TryFrom<&str> for Coord {
-    type Error = Error;
+    type Error = AppError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let parts: Vec<&str> = value.split_inclusive(char::is_alphabetic).collect();
         if parts.len() != 2 {
-            return Err(Error {
-                error: String::from("Malformed cell"),
-            });
+            return Err(AppError::BadRequest(String::from("Malformed cell")));
         }
 
         let c = parts[0].chars().next();
         if c.is_none() {
-            return Err(Error {
-                error: String::from("Malformed cell"),
-            });
+            return Err(AppError::BadRequest(String::from("Malformed cell")));
         }
 
         let c = c.unwrap();
         if !c.is_ascii_uppercase() {
-            return Err(Error {
-                error: String::from("Malformed cell"),
-            });
+            return Err(AppError::BadRequest(String::from("Malformed cell")));
         }
 
         let mut char_bytes: [u8; 1] = [0; 1];
@@ -83,15 +90,16 @@ impl TryFrom<&str> for Coord {
 
         let row = parts[1].parse::<usize>();
         if row.is_err() {
-            return Err(Error {
-                error: format!("Malformed cell: {}", row.unwrap_err()),
-            });
+            return Err(AppError::BadRequest(format!(
+                "Malformed cell: {}",
+                row.unwrap_err()
+            )));
         }
         let row = row.unwrap();
         if row == 0 {
-            return Err(Error {
-                error: String::from("Malformed cell: should start at 1"),
-            });
+            return Err(AppError::BadRequest(String::from(
+                "Malformed cell: should start at 1",
+            )));
         }
 
         let column = char_bytes[0] as usize - 65;
@@ -101,27 +109,27 @@ impl TryFrom<&str> for Coord {
 }
 
 impl TryFrom<&web::Json<CreateMazeHttpRequest>> for CreateMaze {
-    type Error = Error;
+    type Error = AppError;
 
     fn try_from(value: &web::Json<CreateMazeHttpRequest>) -> Result<Self, Self::Error> {
         let coords: Vec<&str> = value.grid_size.split('x').collect();
         if coords.len() != 2 {
-            return Err(Error {
-                error: String::from("Malformed grid size"),
-            });
+            return Err(AppError::BadRequest(String::from("Malformed grid size")));
         }
 
         let grid_size_width = coords[0].parse::<usize>();
         if grid_size_width.is_err() {
-            return Err(Error {
-                error: format!("Malformed grid size: {}", grid_size_width.unwrap_err()),
-            });
+            return Err(AppError::BadRequest(format!(
+                "Malformed grid size: {}",
+                grid_size_width.unwrap_err()
+            )));
         }
         let grid_size_height = coords[1].parse::<usize>();
         if grid_size_height.is_err() {
-            return Err(Error {
-                error: format!("Malformed grid size: {}", grid_size_height.unwrap_err()),
-            });
+            return Err(AppError::BadRequest(format!(
+                "Malformed grid size: {}",
+                grid_size_height.unwrap_err()
+            )));
         }
 
         let entrance = value.entrance.as_str().try_into()?;
@@ -139,9 +147,71 @@ impl TryFrom<&web::Json<CreateMazeHttpRequest>> for CreateMaze {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Error {
-    error: String,
+/// Application level error surfaced to clients with a semantically correct
+/// HTTP status code.
+#[derive(Debug)]
+enum AppError {
+    /// The request was malformed (e.g. an unparseable cell or grid size, or an
+    /// unsolvable maze).
+    BadRequest(String),
+    /// The requested maze does not exist.
+    NotFound(String),
+    /// The SQLite layer failed.
+    Database(String),
+    /// (De)serializing the maze blob failed.
+    Serialization(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::BadRequest(msg)
+            | AppError::NotFound(msg)
+            | AppError::Database(msg)
+            | AppError::Serialization(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(String::from("Maze not found"))
+            }
+            other => AppError::Database(format!("Database error: {}", other)),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Serialization(format!("Failed to (de)serialize maze: {}", err))
+    }
+}
+
+impl From<r2d2::Error> for AppError {
+    fn from(err: r2d2::Error) -> Self {
+        AppError::Database(format!("Database pool error: {}", err))
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Database(_) | AppError::Serialization(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(json!({ "error": self.to_string() }))
+    }
 }
 
 struct MazePath {
@@ -149,7 +219,53 @@ struct MazePath {
     leaf: Position,
 }
 
-// BFS traversal
+/// Pathfinding strategy selected through the `?algorithm=` query parameter.
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Algorithm {
+    /// Breadth-first search: a true shortest path on the unweighted grid.
+    #[default]
+    Bfs,
+    /// A* with a Manhattan-distance-to-nearest-exit heuristic.
+    Astar,
+}
+
+/// The (at most four) in-bounds neighbours of a cell. Walls are included;
+/// callers skip them.
+fn neighbours(pos: Position, width: usize, height: usize) -> Vec<Position> {
+    let coord = Coord::from_pos(pos, width);
+    let adjacents: [(isize, isize); 4] = [
+        (coord.0 as isize + 1, coord.1 as isize),
+        (coord.0 as isize - 1, coord.1 as isize),
+        (coord.0 as isize, coord.1 as isize + 1),
+        (coord.0 as isize, coord.1 as isize - 1),
+    ];
+
+    adjacents
+        .iter()
+        .filter(|(x, y)| *x >= 0 && *y >= 0 && (*x as usize) < width && (*y as usize) < height)
+        .map(|(x, y)| Coord(*x as usize, *y as usize).to_pos(width))
+        .collect()
+}
+
+/// Manhattan distance from `pos` to the closest of the four corner exits.
+fn heuristic(pos: Position, width: usize, height: usize) -> usize {
+    let coord = Coord::from_pos(pos, width);
+    let exits: [(usize, usize); 4] = [
+        (0, 0),
+        (width - 1, 0),
+        (0, height - 1),
+        (width - 1, height - 1),
+    ];
+    exits
+        .iter()
+        .map(|(ex, ey)| coord.0.abs_diff(*ex) + coord.1.abs_diff(*ey))
+        .min()
+        .unwrap()
+}
+
+// BFS traversal: a FIFO queue explores the grid level by level, so the first
+// exit reached is always at the shortest distance from the entrance.
 fn shortest_path(
     maze: &[MazeCellKind],
     entrance_pos: Position,
@@ -162,12 +278,12 @@ fn shortest_path(
     let mut parents: Vec<Option<Position>> = Vec::with_capacity(width * height);
     parents.resize(parents.capacity(), None);
 
-    let mut work: Vec<Position> = Vec::with_capacity(10);
-    work.push(entrance_pos);
+    let mut work: VecDeque<Position> = VecDeque::with_capacity(10);
+    work.push_back(entrance_pos);
 
     explored[entrance_pos] = true;
 
-    while let Some(work_pos) = work.pop() {
+    while let Some(work_pos) = work.pop_front() {
         if maze[work_pos] == MazeCellKind::Exit {
             return Some(MazePath {
                 parents,
@@ -175,36 +291,70 @@ fn shortest_path(
             });
         }
 
-        let work_coord = Coord::from_pos(work_pos, width);
-        let adjacents: [(isize, isize); 4] = [
-            (work_coord.0 as isize + 1, work_coord.1 as isize),
-            (work_coord.0 as isize - 1, work_coord.1 as isize),
-            (work_coord.0 as isize, work_coord.1 as isize + 1),
-            (work_coord.0 as isize, work_coord.1 as isize - 1),
-        ];
-
-        for adjacent in adjacents {
-            // Out of bounds
-            if adjacent.0 < 0
-                || adjacent.1 < 0
-                || adjacent.0 as usize >= width
-                || adjacent.1 as usize >= height
-            {
+        for adjacent_pos in neighbours(work_pos, width, height) {
+            // Do not go through walls
+            if maze[adjacent_pos] == MazeCellKind::Wall {
                 continue;
             }
 
-            let adjacent_pos = Coord(adjacent.0 as usize, adjacent.1 as usize).to_pos(width);
-            let kind = maze[adjacent_pos];
+            if !explored[adjacent_pos] {
+                explored[adjacent_pos] = true;
+                parents[adjacent_pos] = Some(work_pos);
+                work.push_back(adjacent_pos);
+            }
+        }
+    }
+
+    None
+}
+
+// A* traversal: a min-heap keyed on `f = g + h`, where `g` is the number of
+// steps from the entrance and `h` the Manhattan distance to the nearest exit.
+// On sparse grids this visits far fewer cells than BFS while still returning a
+// provably shortest path (of the same length as BFS's, though ties may resolve
+// to a different route).
+fn astar_path(
+    maze: &[MazeCellKind],
+    entrance_pos: Position,
+    width: usize,
+    height: usize,
+) -> Option<MazePath> {
+    let mut parents: Vec<Option<Position>> = Vec::with_capacity(width * height);
+    parents.resize(parents.capacity(), None);
+
+    let mut best_g: Vec<usize> = Vec::with_capacity(width * height);
+    best_g.resize(best_g.capacity(), usize::MAX);
+
+    // Ordered by `(f, g, position)`, smallest first.
+    let mut work: BinaryHeap<Reverse<(usize, usize, Position)>> = BinaryHeap::new();
+    best_g[entrance_pos] = 0;
+    work.push(Reverse((heuristic(entrance_pos, width, height), 0, entrance_pos)));
 
+    while let Some(Reverse((_f, g, work_pos))) = work.pop() {
+        // A cheaper route to this cell was already finalized.
+        if g > best_g[work_pos] {
+            continue;
+        }
+
+        if maze[work_pos] == MazeCellKind::Exit {
+            return Some(MazePath {
+                parents,
+                leaf: work_pos,
+            });
+        }
+
+        for adjacent_pos in neighbours(work_pos, width, height) {
             // Do not go through walls
-            if kind == MazeCellKind::Wall {
+            if maze[adjacent_pos] == MazeCellKind::Wall {
                 continue;
             }
 
-            if !explored[adjacent_pos] {
-                explored[adjacent_pos] = true;
+            let tentative_g = g + 1;
+            if tentative_g < best_g[adjacent_pos] {
+                best_g[adjacent_pos] = tentative_g;
                 parents[adjacent_pos] = Some(work_pos);
-                work.push(adjacent_pos);
+                let f = tentative_g + heuristic(adjacent_pos, width, height);
+                work.push(Reverse((f, tentative_g, adjacent_pos)));
             }
         }
     }
@@ -240,23 +390,79 @@ fn make_maze(create_maze: &CreateMaze) -> Vec<MazeCellKind> {
     maze
 }
 
-fn draw_maze(maze: &[MazeCellKind], path: &[Position], width: usize, height: usize) {
-    for y in 0..height {
-        for x in 0..height {
-            let pos = Coord(x, y).to_pos(width);
-            match path.iter().find(|p| **p == pos) {
-                Some(p) if *p == pos => {
-                    print!("*");
-                }
-                _ => match maze[pos] {
-                    MazeCellKind::Wall => print!("x"),
-                    MazeCellKind::Empty => print!("."),
-                    MazeCellKind::Exit => print!("o"),
-                    MazeCellKind::Entry => print!("e"),
-                },
-            }
-        }
-        println!();
+/// Row-major walk over the grid, yielding `(x, y, pos)` for every cell. Drives
+/// the SVG cell geometry in [`render_svg`].
+fn cells(width: usize, height: usize) -> impl Iterator<Item = (usize, usize, Position)> {
+    (0..height).flat_map(move |y| (0..width).map(move |x| (x, y, Coord(x, y).to_pos(width))))
+}
+
+/// Side length, in SVG user units, of a single maze cell.
+const CELL_SIZE: usize = 24;
+
+/// Render the grid — walls, entrance, exits and the highlighted solution path —
+/// as a standalone SVG document. Walks the cell geometry via [`cells`], one
+/// `<rect>` per cell.
+fn render_svg(maze: &[MazeCellKind], path: &[Position], width: usize, height: usize) -> String {
+    let w = width * CELL_SIZE;
+    let h = height * CELL_SIZE;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\">\n"
+    );
+    svg.push_str(&format!("<rect width=\"{w}\" height=\"{h}\" fill=\"#ffffff\"/>\n"));
+
+    for (x, y, pos) in cells(width, height) {
+        let fill = match maze[pos] {
+            MazeCellKind::Entry => "#3498db",
+            MazeCellKind::Exit => "#2ecc71",
+            MazeCellKind::Wall => "#333333",
+            MazeCellKind::Empty if path.contains(&pos) => "#e8491d",
+            MazeCellKind::Empty => "#ffffff",
+        };
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{CELL_SIZE}\" height=\"{CELL_SIZE}\" \
+             fill=\"{fill}\" stroke=\"#cccccc\" stroke-width=\"1\"/>\n",
+            x * CELL_SIZE,
+            y * CELL_SIZE,
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Rasterize an SVG document into PNG bytes.
+fn render_png(svg: &str) -> Result<Vec<u8>, AppError> {
+    let tree = resvg::usvg::Tree::from_str(svg, &resvg::usvg::Options::default())
+        .map_err(|err| AppError::Serialization(format!("Failed to parse SVG: {}", err)))?;
+
+    let size = tree.size().to_int_size();
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| AppError::BadRequest(String::from("Maze image has an invalid size")))?;
+
+    resvg::render(&tree, resvg::tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+    pixmap
+        .encode_png()
+        .map_err(|err| AppError::Serialization(format!("Failed to encode PNG: {}", err)))
+}
+
+/// Render a solved maze as an SVG (`png == false`) or PNG image response.
+fn solution_image_response(
+    maze: &[MazeCellKind],
+    path: &[Position],
+    width: usize,
+    height: usize,
+    png: bool,
+) -> Result<HttpResponse, AppError> {
+    let svg = render_svg(maze, path, width, height);
+    if png {
+        Ok(HttpResponse::Ok()
+            .content_type("image/png")
+            .body(render_png(&svg)?))
+    } else {
+        Ok(HttpResponse::Ok().content_type("image/svg+xml").body(svg))
     }
 }
 
@@ -265,71 +471,47 @@ fn create_maze_table_in_db(conn: &Connection) {
         .expect("Failed to create maze table");
 }
 
-fn create_maze_in_db(conn: &Connection, create_maze: &CreateMaze) -> Result<usize, Error> {
-    let blob = match serde_json::to_string(&create_maze) {
-        Ok(blob) => blob,
-        Err(err) => {
-            return Err(Error {
-                error: format!("Failed to serialize maze to JSON: {}", err),
-            })
-        }
-    };
+fn create_maze_in_db(conn: &Connection, create_maze: &CreateMaze) -> Result<usize, AppError> {
+    let blob = serde_json::to_string(&create_maze)?;
 
-    match conn.query_row(
+    let id = conn.query_row(
         "INSERT INTO mazes VALUES (?) RETURNING rowid",
         [&blob],
         |row| row.get(0),
-    ) {
-        Ok(res) => Ok(res),
-        Err(err) => Err(Error {
-            error: format!("Failed to save maze in database: {}", err),
-        }),
-    }
+    )?;
+    Ok(id)
 }
 
-async fn create_maze(req: web::Json<CreateMazeHttpRequest>) -> HttpResponse {
-    let create_maze: CreateMaze = match (&req).try_into() {
-        Err(err) => {
-            return HttpResponse::BadGateway().json(err);
-        }
-        Ok(v) => v,
-    };
-
-    let conn = match Connection::open("maze") {
-        Ok(conn) => conn,
-        Err(err) => {
-            return HttpResponse::BadGateway().json(Error {
-                error: err.to_string(),
-            })
-        }
-    };
-
-    match create_maze_in_db(&conn, &create_maze) {
-        Ok(id) => HttpResponse::Ok().json(json!({
-            "id": id,
-            "maze": create_maze,
-        })),
-        Err(err) => HttpResponse::BadGateway().json(err),
-    }
+async fn create_maze(
+    req: web::Json<CreateMazeHttpRequest>,
+    pool: web::Data<DbPool>,
+    metrics: web::Data<Metrics>,
+) -> Result<HttpResponse, AppError> {
+    let create_maze: CreateMaze = (&req).try_into()?;
+
+    let conn = pool.get()?;
+    let id = create_maze_in_db(&conn, &create_maze)?;
+
+    metrics.maze_creations.inc();
+    metrics
+        .grid_cells
+        .observe((create_maze.grid_size.0 * create_maze.grid_size.1) as f64);
+
+    Ok(HttpResponse::Ok().json(json!({
+        "id": id,
+        "maze": create_maze,
+    })))
 }
 
-fn get_maze_from_db(conn: &Connection, id: usize) -> Result<CreateMaze, Error> {
-    let blob: String = match conn.query_row(
+fn get_maze_from_db(conn: &Connection, id: usize) -> Result<CreateMaze, AppError> {
+    let blob: String = conn.query_row(
         "SELECT maze FROM mazes WHERE rowid = ? LIMIT 1",
         [id],
         |row| row.get(0),
-    ) {
-        Ok(blob) => blob,
-        Err(err) => {
-            return Err(Error {
-                error: format!("Failed to read maze from database: {}", err),
-            });
-        }
-    };
+    )?;
 
-    serde_json::from_str(&blob).map_err(|err| Error {
-        error: format!("Failed to deserialize maze from JSON: {}", err),
-    })
+    let maze = serde_json::from_str(&blob)?;
+    Ok(maze)
 }
 
 fn collect_path(path: &MazePath) -> Vec<Position> {
@@ -346,62 +528,264 @@ fn collect_path(path: &MazePath) -> Vec<Position> {
     path_pos
 }
 
-async fn solve_maze(path: web::Path<usize>) -> HttpResponse {
+#[derive(Debug, Deserialize, Default)]
+struct SolveQuery {
+    #[serde(default)]
+    algorithm: Algorithm,
+}
+
+async fn solve_maze(
+    path: web::Path<usize>,
+    query: web::Query<SolveQuery>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    metrics: web::Data<Metrics>,
+) -> Result<HttpResponse, AppError> {
     let maze_id: usize = path.into_inner();
+    metrics.solve_requests.inc();
 
-    let conn = match Connection::open("maze") {
-        Ok(conn) => conn,
-        Err(err) => {
-            return HttpResponse::BadGateway().json(Error {
-                error: err.to_string(),
-            })
-        }
-    };
+    let conn = pool.get()?;
 
-    let create_maze = match get_maze_from_db(&conn, maze_id) {
-        Err(err) => {
-            return HttpResponse::BadGateway().json(err);
-        }
-        Ok(crate_maze) => crate_maze,
-    };
+    let create_maze = get_maze_from_db(&conn, maze_id)?;
     let maze = make_maze(&create_maze);
     let width = create_maze.grid_size.0;
     let height = create_maze.grid_size.1;
 
     let entrance_pos = Coord::to_pos(&create_maze.entrance, width);
-    let path = match shortest_path(&maze, entrance_pos, width, height) {
-        None => {
-            return HttpResponse::BadRequest().json(Error {
-                error: String::from("No path found, invalid maze"),
-            });
-        }
-        Some(path) => path,
-    };
+    let solve_start = Instant::now();
+    let path = match query.algorithm {
+        Algorithm::Bfs => shortest_path(&maze, entrance_pos, width, height),
+        Algorithm::Astar => astar_path(&maze, entrance_pos, width, height),
+    }
+    .ok_or_else(|| AppError::BadRequest(String::from("No path found, invalid maze")))?;
 
     let path = collect_path(&path);
-    draw_maze(&maze, &path, width, height);
+    metrics.solve_latency.observe(solve_start.elapsed().as_secs_f64());
+    metrics.path_length.observe(path.len() as f64);
+
+    // Content negotiation: a client that asks for an image via `Accept` gets one
+    // here, without needing the `.svg`/`.png` path extension.
+    let accept = req
+        .headers()
+        .get("accept")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if accept.contains("image/png") {
+        return solution_image_response(&maze, &path, width, height, true);
+    }
+    if accept.contains("image/svg") {
+        return solution_image_response(&maze, &path, width, height, false);
+    }
 
     let human_readable_path = path
         .iter()
         .map(|pos| format!("{}", Coord::from_pos(*pos, width)))
         .collect::<Vec<String>>();
-    HttpResponse::Ok().json(human_readable_path)
+    Ok(HttpResponse::Ok().json(human_readable_path))
+}
+
+async fn metrics_endpoint(metrics: web::Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.gather())
+}
+
+/// Build the maze grid and solve it, returning the cell kinds, the solution
+/// path from entrance to exit, and the grid width and height, ready to be
+/// rendered.
+fn solve_to_maze_and_path(
+    create_maze: &CreateMaze,
+) -> Option<(Vec<MazeCellKind>, Vec<Position>, usize, usize)> {
+    let maze = make_maze(create_maze);
+    let width = create_maze.grid_size.0;
+    let height = create_maze.grid_size.1;
+
+    let entrance_pos = Coord::to_pos(&create_maze.entrance, width);
+    let path = shortest_path(&maze, entrance_pos, width, height)?;
+    let path = collect_path(&path);
+    Some((maze, path, width, height))
+}
+
+async fn solve_maze_image(
+    params: web::Path<(usize, String)>,
+    pool: web::Data<DbPool>,
+    metrics: web::Data<Metrics>,
+) -> Result<HttpResponse, AppError> {
+    let (maze_id, ext) = params.into_inner();
+    metrics.solve_requests.inc();
+
+    let conn = pool.get()?;
+    let create_maze = get_maze_from_db(&conn, maze_id)?;
+
+    let (maze, path, width, height) = solve_to_maze_and_path(&create_maze)
+        .ok_or_else(|| AppError::BadRequest(String::from("No path found, invalid maze")))?;
+    metrics.path_length.observe(path.len() as f64);
+
+    let png = ext.eq_ignore_ascii_case("png");
+    solution_image_response(&maze, &path, width, height, png)
+}
+
+#[Object]
+impl Coord {
+    /// Zero-based horizontal index inside the grid.
+    async fn x(&self) -> usize {
+        self.0
+    }
+
+    /// Zero-based vertical index inside the grid.
+    async fn y(&self) -> usize {
+        self.1
+    }
+
+    /// Human readable cell label, e.g. `A1`.
+    async fn label(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+#[Object]
+impl CreateMaze {
+    async fn entrance(&self) -> &Coord {
+        &self.entrance
+    }
+
+    async fn grid_size(&self) -> Vec<usize> {
+        vec![self.grid_size.0, self.grid_size.1]
+    }
+
+    async fn walls(&self) -> &[Coord] {
+        &self.walls
+    }
+}
+
+#[derive(SimpleObject)]
+struct CreateMazeResult {
+    id: usize,
+    maze: CreateMaze,
+}
+
+fn solve_maze_to_coords(create_maze: &CreateMaze) -> Option<Vec<Coord>> {
+    let maze = make_maze(create_maze);
+    let width = create_maze.grid_size.0;
+    let height = create_maze.grid_size.1;
+
+    let entrance_pos = Coord::to_pos(&create_maze.entrance, width);
+    let path = shortest_path(&maze, entrance_pos, width, height)?;
+    Some(
+        collect_path(&path)
+            .into_iter()
+            .map(|pos| Coord::from_pos(pos, width))
+            .collect(),
+    )
+}
+
+struct Query;
+
+#[Object]
+impl Query {
+    /// Fetch a stored maze definition by its identifier.
+    async fn maze(&self, ctx: &Context<'_>, id: usize) -> async_graphql::Result<CreateMaze> {
+        let conn = ctx.data::<DbPool>()?.get()?;
+        get_maze_from_db(&conn, id).map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
+    /// Solve a stored maze and return the path from the entrance to the first exit.
+    async fn solution(&self, ctx: &Context<'_>, id: usize) -> async_graphql::Result<Vec<Coord>> {
+        let conn = ctx.data::<DbPool>()?.get()?;
+        let create_maze =
+            get_maze_from_db(&conn, id).map_err(|err| async_graphql::Error::new(err.to_string()))?;
+        solve_maze_to_coords(&create_maze)
+            .ok_or_else(|| async_graphql::Error::new("No path found, invalid maze"))
+    }
+}
+
+struct Mutation;
+
+#[Object]
+impl Mutation {
+    /// Create and persist a maze, returning its identifier and parsed definition.
+    async fn create_maze(
+        &self,
+        ctx: &Context<'_>,
+        entrance: String,
+        grid_size: String,
+        walls: Vec<String>,
+    ) -> async_graphql::Result<CreateMazeResult> {
+        let req = web::Json(CreateMazeHttpRequest {
+            entrance,
+            grid_size,
+            walls,
+        });
+        let create_maze: CreateMaze = (&req)
+            .try_into()
+            .map_err(|err: AppError| async_graphql::Error::new(err.to_string()))?;
+
+        let conn = ctx.data::<DbPool>()?.get()?;
+        let id = create_maze_in_db(&conn, &create_maze)
+            .map_err(|err| async_graphql::Error::new(err.to_string()))?;
+
+        Ok(CreateMazeResult {
+            id,
+            maze: create_maze,
+        })
+    }
+}
+
+type MazeSchema = Schema<Query, Mutation, EmptySubscription>;
+
+async fn graphql_handler(schema: web::Data<MazeSchema>, req: GraphQLRequest) -> GraphQLResponse {
+    schema.execute(req.into_inner()).await.into()
+}
+
+async fn graphql_playground() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(async_graphql::http::playground_source(
+            async_graphql::http::GraphQLPlaygroundConfig::new("/graphql"),
+        ))
 }
 
 #[actix_web::main] // or #[tokio::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init();
 
-    let conn = Connection::open("maze").expect("Failed to open db connection");
-    create_maze_table_in_db(&conn);
+    // A single pool, built once and shared by every worker. Each connection is
+    // put into WAL mode and given a busy-timeout when it is first opened, so
+    // concurrent readers do not block each other and writers wait politely.
+    let manager = SqliteConnectionManager::file("maze").with_init(|conn| {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+    });
+    let pool: DbPool = r2d2::Pool::new(manager).expect("Failed to create db pool");
+
+    create_maze_table_in_db(&pool.get().expect("Failed to check out db connection"));
+
+    let schema = Schema::build(Query, Mutation, EmptySubscription)
+        .data(pool.clone())
+        .finish();
+    let metrics = Metrics::new();
 
-    HttpServer::new(|| {
+    HttpServer::new(move || {
         App::new()
             // enable logger
             .wrap(middleware::Logger::default())
+            .wrap(RequestMetrics::new(metrics.clone()))
             .app_data(web::JsonConfig::default().limit(4096)) // <- limit size of the payload (global configuration)
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::new(schema.clone()))
+            .app_data(web::Data::new(metrics.clone()))
             .service(web::resource("/maze").route(web::post().to(create_maze)))
             .service(web::resource("/maze/{id}/solution").route(web::get().to(solve_maze)))
+            .service(
+                web::resource("/maze/{id}/solution.{ext}")
+                    .route(web::get().to(solve_maze_image)),
+            )
+            .service(web::resource("/metrics").route(web::get().to(metrics_endpoint)))
+            .service(
+                web::resource("/graphql")
+                    .route(web::post().to(graphql_handler))
+                    .route(web::get().to(graphql_playground)),
+            )
     })
     .bind(("127.0.0.1", 8080))?
     .run()
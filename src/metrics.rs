@@ -0,0 +1,185 @@
+//! Prometheus metrics for the maze service.
+//!
+//! A single [`Metrics`] value is built once in `main`, shared with the handlers
+//! through `App::app_data`, and exposed in the Prometheus text exposition
+//! format at `GET /metrics`. The [`RequestMetrics`] middleware runs alongside
+//! `middleware::Logger` and records the duration and outcome of every request.
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use prometheus::{
+    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Domain and HTTP metrics registered against a private [`Registry`].
+///
+/// The prometheus metric handles are internally reference counted, so cloning a
+/// `Metrics` is cheap and every clone points at the same underlying series.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    /// Total number of mazes created.
+    pub maze_creations: IntCounter,
+    /// Total number of solve requests served.
+    pub solve_requests: IntCounter,
+    /// Time spent solving a maze, in seconds.
+    pub solve_latency: Histogram,
+    /// Length (in cells) of the returned solution paths.
+    pub path_length: Histogram,
+    /// Number of cells in the grids submitted to `create_maze`.
+    pub grid_cells: Histogram,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+}
+
+impl Metrics {
+    /// Build and register every metric against a fresh registry.
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let maze_creations =
+            IntCounter::new("maze_creations_total", "Total number of mazes created").unwrap();
+        let solve_requests =
+            IntCounter::new("maze_solve_requests_total", "Total number of solve requests").unwrap();
+        let solve_latency = Histogram::with_opts(HistogramOpts::new(
+            "maze_solve_latency_seconds",
+            "Time spent solving a maze",
+        ))
+        .unwrap();
+        let path_length = Histogram::with_opts(
+            HistogramOpts::new("maze_solution_path_length", "Length of the solved maze path")
+                .buckets(prometheus::exponential_buckets(2.0, 2.0, 10).unwrap()),
+        )
+        .unwrap();
+        let grid_cells = Histogram::with_opts(
+            HistogramOpts::new("maze_grid_cells", "Number of cells in a created maze")
+                .buckets(prometheus::exponential_buckets(4.0, 2.0, 12).unwrap()),
+        )
+        .unwrap();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total number of HTTP requests"),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request duration"),
+            &["method", "path"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(maze_creations.clone())).unwrap();
+        registry.register(Box::new(solve_requests.clone())).unwrap();
+        registry.register(Box::new(solve_latency.clone())).unwrap();
+        registry.register(Box::new(path_length.clone())).unwrap();
+        registry.register(Box::new(grid_cells.clone())).unwrap();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+
+        Metrics {
+            registry,
+            maze_creations,
+            solve_requests,
+            solve_latency,
+            path_length,
+            grid_cells,
+            http_requests,
+            http_duration,
+        }
+    }
+
+    /// Render every metric in the Prometheus text exposition format.
+    pub fn gather(&self) -> String {
+        let encoder = TextEncoder::new();
+        encoder
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Actix middleware recording the duration and outcome of each request.
+pub struct RequestMetrics {
+    metrics: Metrics,
+}
+
+impl RequestMetrics {
+    pub fn new(metrics: Metrics) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware {
+            service: Rc::new(service),
+            metrics: self.metrics.clone(),
+        }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<S>,
+    metrics: Metrics,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let metrics = self.metrics.clone();
+        let service = self.service.clone();
+        let method = req.method().to_string();
+        // Use the routing pattern rather than the concrete path so that
+        // `/maze/1/solution` and `/maze/2/solution` collapse into one series.
+        let path = req
+            .match_pattern()
+            .unwrap_or_else(|| req.path().to_string());
+        let start = Instant::now();
+
+        Box::pin(async move {
+            let res = service.call(req).await?;
+            let status = res.status().as_u16().to_string();
+            let elapsed = start.elapsed().as_secs_f64();
+
+            metrics
+                .http_requests
+                .with_label_values(&[&method, &path, &status])
+                .inc();
+            metrics
+                .http_duration
+                .with_label_values(&[&method, &path])
+                .observe(elapsed);
+
+            Ok(res)
+        })
+    }
+}